@@ -12,10 +12,22 @@ use fuzzy_matcher::skim::SkimMatcherV2 as Matcher;
 use fuzzy_matcher::FuzzyMatcher;
 use tui::widgets::Widget;
 
-use std::{borrow::Cow, collections::HashMap, path::PathBuf};
+use rayon::prelude::*;
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
 
 use crate::ui::{Prompt, PromptEvent};
 use helix_core::Position;
+use helix_event::request_redraw;
 use helix_view::{
     document::canonicalize_path,
     editor::Action,
@@ -28,127 +40,47 @@ pub const MIN_SCREEN_WIDTH_FOR_PREVIEW: u16 = 80;
 /// File path and line number (used to align and highlight a line)
 type FileLocation = (PathBuf, Option<usize>);
 
+/// What a `Picker` should show in its preview pane for the current
+/// selection. Lets pickers other than `FilePicker` (symbols, buffers,
+/// commands, ...) show documentation or other text previews through the
+/// same split layout instead of only ever opening a file.
+pub enum Preview {
+    File(PathBuf, Option<usize>),
+    Text(Cow<'static, str>),
+    None,
+}
+
+/// Thin wrapper around `Picker` that supplies a `Preview::File` preview
+/// backed by a path/line lookup. The document cache that backs file
+/// previews lives in `Picker` itself, not here.
 pub struct FilePicker<T> {
     picker: Picker<T>,
-    /// Caches paths to documents
-    preview_cache: HashMap<PathBuf, Document>,
-    /// Given an item in the picker, return the file path and line number to display.
-    file_fn: Box<dyn Fn(&Editor, &T) -> Option<FileLocation>>,
 }
 
-impl<T> FilePicker<T> {
+impl<T: Send + Sync + 'static> FilePicker<T> {
     pub fn new(
         options: Vec<T>,
-        format_fn: impl Fn(&T) -> Cow<str> + 'static,
+        format_fn: impl Fn(&T) -> Cow<str> + Send + Sync + 'static,
         callback_fn: impl Fn(&mut Editor, &T, Action) + 'static,
         preview_fn: impl Fn(&Editor, &T) -> Option<FileLocation> + 'static,
     ) -> Self {
-        Self {
-            picker: Picker::new(false, options, format_fn, callback_fn),
-            preview_cache: HashMap::new(),
-            file_fn: Box::new(preview_fn),
-        }
-    }
-
-    fn current_file(&self, editor: &Editor) -> Option<FileLocation> {
-        self.picker
-            .selection()
-            .and_then(|current| (self.file_fn)(editor, current))
-            .and_then(|(path, line)| canonicalize_path(&path).ok().zip(Some(line)))
-    }
+        let picker = Picker::new(false, options, format_fn, callback_fn).with_preview(
+            move |editor, option| match preview_fn(editor, option) {
+                Some((path, line)) => Preview::File(path, line),
+                None => Preview::None,
+            },
+        );
 
-    fn calculate_preview(&mut self, editor: &Editor) {
-        if let Some((path, _line)) = self.current_file(editor) {
-            if !self.preview_cache.contains_key(&path) && editor.document_by_path(&path).is_none() {
-                // TODO: enable syntax highlighting; blocked by async rendering
-                let doc = Document::open(&path, None, Some(&editor.theme), None).unwrap();
-                self.preview_cache.insert(path, doc);
-            }
-        }
+        Self { picker }
     }
 }
 
-impl<T: 'static> Component for FilePicker<T> {
+impl<T: Send + Sync + 'static> Component for FilePicker<T> {
     fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
-        // +---------+ +---------+
-        // |prompt   | |preview  |
-        // +---------+ |         |
-        // |picker   | |         |
-        // |         | |         |
-        // +---------+ +---------+
-        self.calculate_preview(cx.editor);
-        let render_preview = area.width > MIN_SCREEN_WIDTH_FOR_PREVIEW;
-        let area = inner_rect(area);
-        // -- Render the frame:
-        // clear area
-        let background = cx.editor.theme.get("ui.background");
-        surface.clear_with(area, background);
-
-        let picker_width = if render_preview {
-            area.width / 2
-        } else {
-            area.width
-        };
-
-        let picker_area = Rect::new(area.x, area.y, picker_width, area.height);
-        self.picker.render(picker_area, surface, cx);
-
-        if !render_preview {
-            return;
-        }
-
-        let preview_area = Rect::new(area.x + picker_width, area.y, area.width / 2, area.height);
-
-        // don't like this but the lifetime sucks
-        let block = Block::default().borders(Borders::ALL);
-
-        // calculate the inner area inside the box
-        let mut inner = block.inner(preview_area);
-        // 1 column gap on either side
-        inner.x += 1;
-        inner.width = inner.width.saturating_sub(2);
-
-        block.render(preview_area, surface);
-
-        if let Some((doc, line)) = self.current_file(cx.editor).and_then(|(path, line)| {
-            cx.editor
-                .document_by_path(&path)
-                .or_else(|| self.preview_cache.get(&path))
-                .zip(Some(line))
-        }) {
-            // align to middle
-            let first_line = line.unwrap_or(0).saturating_sub(inner.height as usize / 2);
-            let offset = Position::new(first_line, 0);
-
-            let highlights = EditorView::doc_syntax_highlights(
-                doc,
-                offset,
-                area.height,
-                &cx.editor.theme,
-                &cx.editor.syn_loader,
-            );
-            EditorView::render_text_highlights(
-                doc,
-                offset,
-                inner,
-                surface,
-                &cx.editor.theme,
-                highlights,
-            );
-
-            // highlight the line
-            if let Some(line) = line {
-                for x in inner.left()..inner.right() {
-                    surface
-                        .get_mut(x, inner.y + line.saturating_sub(first_line) as u16)
-                        .set_style(cx.editor.theme.get("ui.selection.primary"));
-                }
-            }
-        }
+        self.picker.render(area, surface, cx);
     }
 
     fn handle_event(&mut self, event: Event, ctx: &mut Context) -> EventResult {
-        // TODO: keybinds for scrolling preview
         self.picker.handle_event(event, ctx)
     }
 
@@ -157,12 +89,29 @@ impl<T: 'static> Component for FilePicker<T> {
     }
 }
 
+/// Number of options scored per background batch before results are sent
+/// back to the UI thread. Keeping batches small is what lets the render
+/// loop show partial matches while a big option set is still scoring.
+const SCORE_BATCH_SIZE: usize = 4096;
+
+/// Maximum number of opened documents kept around for `Preview::File`
+/// previews. Browsing a huge project shouldn't grow memory without limit.
+const PREVIEW_CACHE_CAPACITY: usize = 32;
+
+/// Char indices (into the formatted row text) that the matcher matched
+/// against the pattern, used to highlight exactly why a row matched.
+type MatchIndices = Vec<usize>;
+
+/// One batch of freshly scored options, tagged with the scoring generation
+/// it belongs to so the receiver can discard results from a superseded
+/// (cancelled) scoring pass.
+type ScoreBatch = (usize, Vec<(usize, i64, MatchIndices)>);
+
 pub struct Picker<T> {
-    options: Vec<T>,
+    options: Arc<Vec<T>>,
     // filter: String,
-    matcher: Box<Matcher>,
-    /// (index, score)
-    matches: Vec<(usize, i64)>,
+    /// (index, score, matched char indices)
+    matches: Vec<(usize, i64, MatchIndices)>,
     /// Filter over original options.
     filters: Vec<usize>, // could be optimized into bit but not worth it now
 
@@ -172,15 +121,52 @@ pub struct Picker<T> {
     /// Whether to render in the middle of the area
     render_centered: bool,
 
-    format_fn: Box<dyn Fn(&T) -> Cow<str>>,
+    /// Options marked for a batch action, keyed by index into `options`.
+    /// Enter acts on every marked option, falling back to the option under
+    /// `cursor` when nothing is marked.
+    selections: HashSet<usize>,
+
+    format_fn: Arc<dyn Fn(&T) -> Cow<str> + Send + Sync>,
     callback_fn: Box<dyn Fn(&mut Editor, &T, Action)>,
+
+    /// Bumped every time a new scoring pass is kicked off. Background
+    /// workers compare their captured generation against this before
+    /// publishing results, so a pass made stale by further typing never
+    /// clobbers newer matches.
+    generation: Arc<AtomicUsize>,
+    /// Generation the currently displayed `matches` were scored against.
+    current_generation: usize,
+    /// Set for the duration of a background scoring pass so `render` can
+    /// draw a "matching…" indicator.
+    scoring: Arc<AtomicBool>,
+    results_tx: mpsc::Sender<ScoreBatch>,
+    results_rx: mpsc::Receiver<ScoreBatch>,
+
+    /// Given the current selection, produce what to show in the preview
+    /// pane. `None` (the `Option`, not `Preview::None`) means this picker
+    /// has no preview pane at all.
+    preview_fn: Option<Box<dyn Fn(&Editor, &T) -> Preview>>,
+    /// Caches opened documents for `Preview::File` previews, keyed by path.
+    preview_cache: HashMap<PathBuf, Document>,
+    /// Least-recently-used order of `preview_cache`'s keys, front is oldest.
+    preview_cache_order: VecDeque<PathBuf>,
+    /// Vertical scroll offset of the preview, independent from the picker's
+    /// selection, in number of lines relative to the centered target line.
+    preview_scroll: isize,
+    /// Height of the preview pane as of the last render, used to size
+    /// half-page/full-page scroll jumps.
+    preview_height: u16,
+
+    /// The pattern `matches` was last scored against, so navigation keys
+    /// and no-op edits don't trigger a redundant rescore.
+    last_scored_pattern: String,
 }
 
-impl<T> Picker<T> {
+impl<T: Send + Sync + 'static> Picker<T> {
     pub fn new(
         render_centered: bool,
         options: Vec<T>,
-        format_fn: impl Fn(&T) -> Cow<str> + 'static,
+        format_fn: impl Fn(&T) -> Cow<str> + Send + Sync + 'static,
         callback_fn: impl Fn(&mut Editor, &T, Action) + 'static,
     ) -> Self {
         let prompt = Prompt::new(
@@ -192,63 +178,420 @@ impl<T> Picker<T> {
             },
         );
 
+        let (results_tx, results_rx) = mpsc::channel();
+
         let mut picker = Self {
-            options,
-            matcher: Box::new(Matcher::default()),
+            options: Arc::new(options),
             matches: Vec::new(),
             filters: Vec::new(),
             cursor: 0,
             prompt,
             render_centered,
-            format_fn: Box::new(format_fn),
+            selections: HashSet::new(),
+            format_fn: Arc::new(format_fn),
             callback_fn: Box::new(callback_fn),
+            generation: Arc::new(AtomicUsize::new(0)),
+            current_generation: 0,
+            scoring: Arc::new(AtomicBool::new(false)),
+            results_tx,
+            results_rx,
+            preview_fn: None,
+            preview_cache: HashMap::new(),
+            preview_cache_order: VecDeque::new(),
+            preview_scroll: 0,
+            preview_height: 0,
+            last_scored_pattern: String::new(),
         };
 
-        // TODO: scoring on empty input should just use a fastpath
         picker.score();
 
         picker
     }
 
+    /// Attach a preview renderer, enabling the split preview pane.
+    pub fn with_preview(mut self, preview_fn: impl Fn(&Editor, &T) -> Preview + 'static) -> Self {
+        self.preview_fn = Some(Box::new(preview_fn));
+        self
+    }
+
+    fn current_preview(&self, editor: &Editor) -> Preview {
+        self.selection()
+            .map(|option| match &self.preview_fn {
+                Some(preview_fn) => preview_fn(editor, option),
+                None => Preview::None,
+            })
+            .unwrap_or(Preview::None)
+    }
+
+    /// For `Preview::File` previews, make sure the document is opened (and
+    /// cached) so `render` can draw its contents.
+    fn calculate_preview(&mut self, editor: &Editor) {
+        if let Preview::File(path, _line) = self.current_preview(editor) {
+            let path = match canonicalize_path(&path) {
+                Ok(path) => path,
+                Err(_) => return,
+            };
+
+            if editor.document_by_path(&path).is_some() {
+                return;
+            }
+
+            if self.preview_cache.contains_key(&path) {
+                // already opened (and syntax highlighted) — just bump it to
+                // the back of the LRU order, no need to reopen or reparse
+                self.touch_preview_cache(&path);
+                return;
+            }
+
+            // passing the syntax loader attaches the language's highlight
+            // configuration, so the preview is syntax highlighted like any
+            // other open document; highlights themselves are only computed
+            // for the visible range when `render_preview` runs
+            let doc =
+                Document::open(&path, None, Some(&editor.theme), Some(&editor.syn_loader)).unwrap();
+            self.preview_cache.insert(path.clone(), doc);
+            self.preview_cache_order.push_back(path);
+
+            if self.preview_cache_order.len() > PREVIEW_CACHE_CAPACITY {
+                if let Some(lru_path) = self.preview_cache_order.pop_front() {
+                    self.preview_cache.remove(&lru_path);
+                }
+            }
+        }
+    }
+
+    fn touch_preview_cache(&mut self, path: &Path) {
+        if let Some(pos) = self
+            .preview_cache_order
+            .iter()
+            .position(|cached| cached == path)
+        {
+            let path = self.preview_cache_order.remove(pos).unwrap();
+            self.preview_cache_order.push_back(path);
+        }
+    }
+
+    /// Render the prompt, separator and match list into `area`.
+    fn render_picker(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
+        // clear area
+        let background = cx.editor.theme.get("ui.background");
+        surface.clear_with(area, background);
+
+        // don't like this but the lifetime sucks
+        let block = Block::default().borders(Borders::ALL);
+
+        // calculate the inner area inside the box
+        let inner = block.inner(area);
+
+        block.render(area, surface);
+
+        // -- Render the input bar:
+
+        let area = Rect::new(inner.x + 1, inner.y, inner.width - 1, 1);
+        self.prompt.render(area, surface, cx);
+
+        if self.is_scoring() {
+            let indicator = "matching…";
+            let indicator_style = Style::default().fg(Color::Rgb(90, 89, 119));
+            let x = (area.x + area.width).saturating_sub(indicator.len() as u16 + 1);
+            surface.set_string(x, area.y, indicator, indicator_style);
+        }
+
+        // -- Separator
+        let sep_style = Style::default().fg(Color::Rgb(90, 89, 119));
+        let borders = BorderType::line_symbols(BorderType::Plain);
+        for x in inner.left()..inner.right() {
+            surface
+                .get_mut(x, inner.y + 1)
+                .set_symbol(borders.horizontal)
+                .set_style(sep_style);
+        }
+
+        // -- Render the contents:
+        // subtract the area of the prompt (-2) and current item marker " > " (-3)
+        let inner = Rect::new(inner.x + 3, inner.y + 2, inner.width - 3, inner.height - 2);
+
+        let style = cx.editor.theme.get("ui.text");
+        let selected = Style::default().fg(Color::Rgb(255, 255, 255));
+        let highlighted = cx.editor.theme.get("ui.text.focus");
+
+        let rows = inner.height;
+        let offset = self.cursor / (rows as usize) * (rows as usize);
+
+        let files = self
+            .matches
+            .iter()
+            .skip(offset)
+            .map(|(index, _score, indices)| {
+                (index, self.options.get(*index).unwrap(), indices) // get_unchecked
+            });
+
+        for (i, (index, option, indices)) in files.take(rows as usize).enumerate() {
+            if i == (self.cursor - offset) {
+                surface.set_string(inner.x - 2, inner.y + i as u16, ">", selected);
+            }
+
+            if self.selections.contains(index) {
+                surface.set_string(inner.x - 1, inner.y + i as u16, "●", selected);
+            }
+
+            let row_style = if i == (self.cursor - offset) {
+                selected
+            } else {
+                style
+            };
+
+            surface.set_string_truncated(
+                inner.x,
+                inner.y + i as u16,
+                (self.format_fn)(option),
+                inner.width as usize,
+                row_style,
+                true,
+            );
+
+            // re-style the chars the matcher actually matched on top of the
+            // row so users can see exactly why it matched
+            for &char_idx in indices {
+                let x = inner.x + char_idx as u16;
+                if x >= inner.x + inner.width {
+                    break;
+                }
+                surface
+                    .get_mut(x, inner.y + i as u16)
+                    .set_style(highlighted);
+            }
+        }
+    }
+
+    /// Render the preview pane into `preview_area`. `full_area` is the
+    /// overall (pre-split) picker area; its height is the height the syntax
+    /// highlighter is asked to cover.
+    fn render_preview(
+        &mut self,
+        full_area: Rect,
+        preview_area: Rect,
+        surface: &mut Surface,
+        cx: &mut Context,
+    ) {
+        // don't like this but the lifetime sucks
+        let block = Block::default().borders(Borders::ALL);
+
+        // calculate the inner area inside the box
+        let mut inner = block.inner(preview_area);
+        // 1 column gap on either side
+        inner.x += 1;
+        inner.width = inner.width.saturating_sub(2);
+
+        block.render(preview_area, surface);
+        self.preview_height = inner.height;
+
+        match self.current_preview(cx.editor) {
+            Preview::File(path, line) => {
+                let path = match canonicalize_path(&path) {
+                    Ok(path) => path,
+                    Err(_) => return,
+                };
+
+                if let Some(doc) = cx
+                    .editor
+                    .document_by_path(&path)
+                    .or_else(|| self.preview_cache.get(&path))
+                {
+                    // align to middle, then apply the user's scroll offset
+                    let centered_first_line =
+                        line.unwrap_or(0).saturating_sub(inner.height as usize / 2);
+                    let first_line =
+                        (centered_first_line as isize + self.preview_scroll).max(0) as usize;
+                    let offset = Position::new(first_line, 0);
+
+                    let highlights = EditorView::doc_syntax_highlights(
+                        doc,
+                        offset,
+                        full_area.height,
+                        &cx.editor.theme,
+                        &cx.editor.syn_loader,
+                    );
+                    EditorView::render_text_highlights(
+                        doc,
+                        offset,
+                        inner,
+                        surface,
+                        &cx.editor.theme,
+                        highlights,
+                    );
+
+                    // highlight the line, if it's still within the scrolled view
+                    if let Some(line) = line {
+                        if line >= first_line && line - first_line < inner.height as usize {
+                            for x in inner.left()..inner.right() {
+                                surface
+                                    .get_mut(x, inner.y + (line - first_line) as u16)
+                                    .set_style(cx.editor.theme.get("ui.selection.primary"));
+                            }
+                        }
+                    }
+                }
+            }
+            Preview::Text(text) => {
+                let style = cx.editor.theme.get("ui.text");
+                for (i, line) in text.lines().take(inner.height as usize).enumerate() {
+                    surface.set_string_truncated(
+                        inner.x,
+                        inner.y + i as u16,
+                        line,
+                        inner.width as usize,
+                        style,
+                        true,
+                    );
+                }
+            }
+            Preview::None => {}
+        }
+    }
+
+    /// Kick off a new background scoring pass for the current prompt line,
+    /// cancelling (by generation) whatever pass was previously in flight.
     pub fn score(&mut self) {
-        // need to borrow via pattern match otherwise it complains about simultaneous borrow
-        let Self {
-            ref mut matcher,
-            ref mut matches,
-            ref filters,
-            ref format_fn,
-            ..
-        } = *self;
-
-        let pattern = &self.prompt.line;
-
-        // reuse the matches allocation
-        matches.clear();
-        matches.extend(
-            self.options
-                .iter()
+        let pattern = self.prompt.line.clone();
+        self.last_scored_pattern = pattern.clone();
+
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        self.current_generation = generation;
+
+        // drop whatever stale results are still sitting in the channel
+        while self.results_rx.try_recv().is_ok() {}
+
+        self.matches.clear();
+        self.cursor = 0;
+        self.preview_scroll = 0;
+
+        if pattern.is_empty() {
+            // fastpath: nothing to fuzzy match against, so skip the matcher
+            // and the background thread and just list every filtered option
+            // in its original order
+            self.matches
+                .extend((0..self.options.len()).filter_map(|index| {
+                    if !self.filters.is_empty() {
+                        self.filters.binary_search(&index).ok()?;
+                    }
+                    Some((index, 0, MatchIndices::new()))
+                }));
+            // no pass is in flight, so make sure any stale indicator clears
+            self.scoring.store(false, Ordering::SeqCst);
+            return;
+        }
+
+        let options = Arc::clone(&self.options);
+        let format_fn = Arc::clone(&self.format_fn);
+        let filters = self.filters.clone();
+        let live_generation = Arc::clone(&self.generation);
+        let scoring = Arc::clone(&self.scoring);
+        let results_tx = self.results_tx.clone();
+
+        scoring.store(true, Ordering::SeqCst);
+
+        thread::spawn(move || {
+            let matcher = Matcher::default();
+
+            options
+                .par_chunks(SCORE_BATCH_SIZE)
                 .enumerate()
-                .filter_map(|(index, option)| {
-                    // filter options first before matching
-                    if !filters.is_empty() {
-                        filters.binary_search(&index).ok()?;
+                .for_each(|(chunk_idx, chunk)| {
+                    // bail out as soon as possible once a newer pattern has
+                    // superseded this pass
+                    if live_generation.load(Ordering::SeqCst) != generation {
+                        return;
                     }
-                    // TODO: maybe using format_fn isn't the best idea here
-                    let text = (format_fn)(option);
-                    // TODO: using fuzzy_indices could give us the char idx for match highlighting
-                    matcher
-                        .fuzzy_match(&text, pattern)
-                        .map(|score| (index, score))
-                }),
-        );
-        matches.sort_unstable_by_key(|(_, score)| -score);
 
-        // reset cursor position
-        self.cursor = 0;
+                    let base = chunk_idx * SCORE_BATCH_SIZE;
+                    let batch: Vec<(usize, i64, MatchIndices)> = chunk
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(offset, option)| {
+                            let index = base + offset;
+                            // filter options first before matching
+                            if !filters.is_empty() {
+                                filters.binary_search(&index).ok()?;
+                            }
+                            // TODO: maybe using format_fn isn't the best idea here
+                            let text = (format_fn)(option);
+                            matcher
+                                .fuzzy_indices(&text, &pattern)
+                                .map(|(score, indices)| (index, score, indices))
+                        })
+                        .collect();
+
+                    if !batch.is_empty() {
+                        let _ = results_tx.send((generation, batch));
+                        // wake the compositor so partial matches show up as
+                        // soon as they land, instead of sitting stale until
+                        // the next terminal/editor event happens to repaint
+                        request_redraw();
+                    }
+                });
+
+            if live_generation.load(Ordering::SeqCst) == generation {
+                scoring.store(false, Ordering::SeqCst);
+                // make sure the "matching…" indicator clears promptly too
+                request_redraw();
+            }
+        });
+    }
+
+    /// Pull in whatever batches the background scorer has produced so far,
+    /// discarding anything left over from a cancelled pass.
+    fn drain_results(&mut self) {
+        let mut incoming: Vec<(usize, i64, MatchIndices)> = Vec::new();
+        while let Ok((generation, batch)) = self.results_rx.try_recv() {
+            if generation != self.current_generation {
+                continue;
+            }
+            incoming.extend(batch);
+        }
+
+        if incoming.is_empty() {
+            return;
+        }
+
+        // `self.matches` is already sorted from the previous drain, so only
+        // the freshly arrived batch needs sorting; merging the two sorted
+        // runs is O(n) instead of resorting everything scored so far on
+        // every tick, which would pay back a good share of the per-keystroke
+        // cost the background scoring was meant to move off the critical
+        // path.
+        let key = |(index, score, _indices): &(usize, i64, MatchIndices)| (-*score, *index);
+        incoming.sort_unstable_by_key(key);
+
+        let mut merged = Vec::with_capacity(self.matches.len() + incoming.len());
+        let mut old = self.matches.drain(..).peekable();
+        let mut new = incoming.into_iter().peekable();
+
+        loop {
+            match (old.peek(), new.peek()) {
+                (Some(o), Some(n)) => {
+                    if key(o) <= key(n) {
+                        merged.push(old.next().unwrap());
+                    } else {
+                        merged.push(new.next().unwrap());
+                    }
+                }
+                (Some(_), None) => merged.push(old.next().unwrap()),
+                (None, Some(_)) => merged.push(new.next().unwrap()),
+                (None, None) => break,
+            }
+        }
+
+        self.matches = merged;
+    }
+
+    fn is_scoring(&self) -> bool {
+        self.scoring.load(Ordering::SeqCst)
     }
 
     pub fn move_up(&mut self) {
         self.cursor = self.cursor.saturating_sub(1);
+        self.preview_scroll = 0;
     }
 
     pub fn move_down(&mut self) {
@@ -259,18 +602,42 @@ impl<T> Picker<T> {
         if self.cursor < self.matches.len() - 1 {
             self.cursor += 1;
         }
+        self.preview_scroll = 0;
     }
 
     pub fn selection(&self) -> Option<&T> {
         self.matches
             .get(self.cursor)
-            .map(|(index, _score)| &self.options[*index])
+            .map(|(index, _score, _indices)| &self.options[*index])
+    }
+
+    /// Toggles the mark on the option under `cursor`.
+    pub fn toggle_selection(&mut self) {
+        if let Some((index, _score, _indices)) = self.matches.get(self.cursor) {
+            if !self.selections.remove(index) {
+                self.selections.insert(*index);
+            }
+        }
+    }
+
+    /// The options to act on: every marked option (in ascending index
+    /// order, so the result is deterministic rather than `HashSet`'s
+    /// iteration order), or just the one under `cursor` when nothing is
+    /// marked.
+    pub fn selected_options(&self) -> Vec<&T> {
+        if self.selections.is_empty() {
+            self.selection().into_iter().collect()
+        } else {
+            let mut indices: Vec<usize> = self.selections.iter().copied().collect();
+            indices.sort_unstable();
+            indices.iter().map(|&index| &self.options[index]).collect()
+        }
     }
 
     pub fn save_filter(&mut self) {
         self.filters.clear();
         self.filters
-            .extend(self.matches.iter().map(|(index, _)| *index));
+            .extend(self.matches.iter().map(|(index, _, _)| *index));
         self.filters.sort_unstable(); // used for binary search later
         self.prompt.clear();
     }
@@ -293,7 +660,7 @@ fn inner_rect(area: Rect) -> Rect {
     )
 }
 
-impl<T: 'static> Component for Picker<T> {
+impl<T: Send + Sync + 'static> Component for Picker<T> {
     fn handle_event(&mut self, event: Event, cx: &mut Context) -> EventResult {
         let key_event = match event {
             Event::Key(event) => event,
@@ -307,6 +674,39 @@ impl<T: 'static> Component for Picker<T> {
         })));
 
         match key_event {
+            // Note: deliberately not Ctrl-d/u/f/b — those are already
+            // Prompt's Emacs-style delete-char/kill-to-start/cursor-right/
+            // cursor-left bindings, and stealing them here would break
+            // basic line editing in the search box for every picker with a
+            // preview pane.
+            KeyEvent {
+                code: KeyCode::PageDown,
+                ..
+            } if self.preview_fn.is_some() => {
+                let full_page = (self.preview_height as isize).max(1);
+                self.preview_scroll += full_page;
+            }
+            KeyEvent {
+                code: KeyCode::PageUp,
+                ..
+            } if self.preview_fn.is_some() => {
+                let full_page = (self.preview_height as isize).max(1);
+                self.preview_scroll -= full_page;
+            }
+            KeyEvent {
+                code: KeyCode::Char('d'),
+                modifiers: KeyModifiers::ALT,
+            } if self.preview_fn.is_some() => {
+                let half_page = (self.preview_height as isize / 2).max(1);
+                self.preview_scroll += half_page;
+            }
+            KeyEvent {
+                code: KeyCode::Char('u'),
+                modifiers: KeyModifiers::ALT,
+            } if self.preview_fn.is_some() => {
+                let half_page = (self.preview_height as isize / 2).max(1);
+                self.preview_scroll -= half_page;
+            }
             KeyEvent {
                 code: KeyCode::Up, ..
             }
@@ -324,15 +724,20 @@ impl<T: 'static> Component for Picker<T> {
                 code: KeyCode::Down,
                 ..
             }
-            | KeyEvent {
-                code: KeyCode::Tab, ..
-            }
             | KeyEvent {
                 code: KeyCode::Char('n'),
                 modifiers: KeyModifiers::CONTROL,
             } => {
                 self.move_down();
             }
+            KeyEvent {
+                code: KeyCode::Tab, ..
+            } => {
+                // toggle the mark on the current option and advance, so a
+                // run of Tabs over unmarked options marks a contiguous block
+                self.toggle_selection();
+                self.move_down();
+            }
             KeyEvent {
                 code: KeyCode::Esc, ..
             }
@@ -346,8 +751,17 @@ impl<T: 'static> Component for Picker<T> {
                 code: KeyCode::Enter,
                 ..
             } => {
-                if let Some(option) = self.selection() {
-                    (self.callback_fn)(&mut cx.editor, option, Action::Replace);
+                // Action::Replace swaps the document in the focused view, so
+                // repeating it would just overwrite itself and silently drop
+                // every marked option but the last. Open the first normally
+                // and the rest in splits so a multi-select Enter genuinely
+                // opens all of them, the same way Ctrl-h/Ctrl-v do.
+                let mut options = self.selected_options().into_iter();
+                if let Some(first) = options.next() {
+                    (self.callback_fn)(&mut cx.editor, first, Action::Replace);
+                }
+                for option in options {
+                    (self.callback_fn)(&mut cx.editor, option, Action::HorizontalSplit);
                 }
                 return close_fn;
             }
@@ -355,7 +769,7 @@ impl<T: 'static> Component for Picker<T> {
                 code: KeyCode::Char('h'),
                 modifiers: KeyModifiers::CONTROL,
             } => {
-                if let Some(option) = self.selection() {
+                for option in self.selected_options() {
                     (self.callback_fn)(&mut cx.editor, option, Action::HorizontalSplit);
                 }
                 return close_fn;
@@ -364,7 +778,7 @@ impl<T: 'static> Component for Picker<T> {
                 code: KeyCode::Char('v'),
                 modifiers: KeyModifiers::CONTROL,
             } => {
-                if let Some(option) = self.selection() {
+                for option in self.selected_options() {
                     (self.callback_fn)(&mut cx.editor, option, Action::VerticalSplit);
                 }
                 return close_fn;
@@ -377,8 +791,11 @@ impl<T: 'static> Component for Picker<T> {
             }
             _ => {
                 if let EventResult::Consumed(_) = self.prompt.handle_event(event, cx) {
-                    // TODO: recalculate only if pattern changed
-                    self.score();
+                    // avoid a full rescore on navigation keys and other
+                    // no-op edits that don't actually change the pattern
+                    if self.prompt.line != self.last_scored_pattern {
+                        self.score();
+                    }
                 }
             }
         }
@@ -387,72 +804,47 @@ impl<T: 'static> Component for Picker<T> {
     }
 
     fn render(&mut self, area: Rect, surface: &mut Surface, cx: &mut Context) {
-        let area = if self.render_centered {
+        // +---------+ +---------+
+        // |prompt   | |preview  |
+        // +---------+ |         |
+        // |picker   | |         |
+        // |         | |         |
+        // +---------+ +---------+
+        self.drain_results();
+        self.calculate_preview(cx.editor);
+
+        let render_preview = self.preview_fn.is_some() && area.width > MIN_SCREEN_WIDTH_FOR_PREVIEW;
+
+        let area = if self.render_centered || self.preview_fn.is_some() {
             inner_rect(area)
         } else {
             area
         };
 
-        // -- Render the frame:
         // clear area
         let background = cx.editor.theme.get("ui.background");
         surface.clear_with(area, background);
 
-        // don't like this but the lifetime sucks
-        let block = Block::default().borders(Borders::ALL);
-
-        // calculate the inner area inside the box
-        let inner = block.inner(area);
-
-        block.render(area, surface);
-
-        // -- Render the input bar:
+        let picker_width = if render_preview {
+            area.width / 2
+        } else {
+            area.width
+        };
 
-        let area = Rect::new(inner.x + 1, inner.y, inner.width - 1, 1);
-        self.prompt.render(area, surface, cx);
+        let picker_area = Rect::new(area.x, area.y, picker_width, area.height);
+        self.render_picker(picker_area, surface, cx);
 
-        // -- Separator
-        let sep_style = Style::default().fg(Color::Rgb(90, 89, 119));
-        let borders = BorderType::line_symbols(BorderType::Plain);
-        for x in inner.left()..inner.right() {
-            surface
-                .get_mut(x, inner.y + 1)
-                .set_symbol(borders.horizontal)
-                .set_style(sep_style);
+        if !render_preview {
+            return;
         }
 
-        // -- Render the contents:
-        // subtract the area of the prompt (-2) and current item marker " > " (-3)
-        let inner = Rect::new(inner.x + 3, inner.y + 2, inner.width - 3, inner.height - 2);
-
-        let style = cx.editor.theme.get("ui.text");
-        let selected = Style::default().fg(Color::Rgb(255, 255, 255));
-
-        let rows = inner.height;
-        let offset = self.cursor / (rows as usize) * (rows as usize);
-
-        let files = self.matches.iter().skip(offset).map(|(index, _score)| {
-            (index, self.options.get(*index).unwrap()) // get_unchecked
-        });
-
-        for (i, (_index, option)) in files.take(rows as usize).enumerate() {
-            if i == (self.cursor - offset) {
-                surface.set_string(inner.x - 2, inner.y + i as u16, ">", selected);
-            }
-
-            surface.set_string_truncated(
-                inner.x,
-                inner.y + i as u16,
-                (self.format_fn)(option),
-                inner.width as usize,
-                if i == (self.cursor - offset) {
-                    selected
-                } else {
-                    style
-                },
-                true,
-            );
-        }
+        let preview_area = Rect::new(
+            area.x + picker_width,
+            area.y,
+            area.width - picker_width,
+            area.height,
+        );
+        self.render_preview(area, preview_area, surface, cx);
     }
 
     fn cursor(&self, area: Rect, editor: &Editor) -> (Option<Position>, CursorKind) {
@@ -467,4 +859,4 @@ impl<T: 'static> Component for Picker<T> {
 
         self.prompt.cursor(area, editor)
     }
-}
\ No newline at end of file
+}